@@ -0,0 +1,50 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Drives a Wii Nunchuck as a mouse via /dev/uinput
+
+extern crate i2cdev;
+extern crate docopt;
+
+use i2cdev::linux::LinuxI2CDevice;
+use i2cdev::sensors::nunchuck::{Nunchuck, NUNCHUCK_SLAVE_ADDR};
+use i2cdev::sensors::nunchuck::uinput;
+use std::env::args;
+use docopt::Docopt;
+
+const USAGE: &'static str = "
+Drive a Wii Nunchuck as a uinput mouse.
+
+Usage:
+  nunchuck_uinput <device>
+  nunchuck_uinput (-h | --help)
+  nunchuck_uinput --version
+
+Options:
+  -h --help    Show this help text.
+  --version    Show version.
+";
+
+fn main() {
+    let args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(args().into_iter()).parse())
+        .unwrap_or_else(|e| e.exit());
+    let device = args.get_str("<device>");
+
+    let i2cdev = LinuxI2CDevice::new(device, NUNCHUCK_SLAVE_ADDR)
+        .unwrap_or_else(|e| panic!("Unable to open {:?}: {:?}", device, e));
+    let mut nunchuck = Nunchuck::new(i2cdev)
+        .unwrap_or_else(|e| panic!("Unable to initialize nunchuck: {:?}", e));
+
+    let mut bridge = uinput::Builder::new()
+        .mode(uinput::Mode::MouseCursor { sensitivity: 10.0 })
+        .build()
+        .unwrap_or_else(|e| panic!("Unable to create uinput device: {:?}", e));
+
+    bridge.run(&mut nunchuck).unwrap_or_else(|e| panic!("Error polling nunchuck: {:?}", e));
+}
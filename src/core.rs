@@ -0,0 +1,119 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Core types shared by every i2cdev backend
+//!
+//! The `I2CDevice` trait is the extension point sensor drivers are
+//! written against.  `i2cdev::linux::LinuxI2CDevice` is the "real"
+//! implementation backed by `/dev/i2c-*`; `i2cdev::mock::MockI2CDevice`
+//! is an in-memory stand-in for unit tests that does not require
+//! access to an actual bus.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Result type used throughout this crate
+pub type I2CResult<T> = Result<T, I2CError>;
+
+/// Catch-all error type for the operations in this crate
+#[derive(Debug)]
+pub enum I2CError {
+    Io(io::Error),
+    Other(&'static str),
+}
+
+impl fmt::Display for I2CError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            I2CError::Io(ref e) => write!(f, "I2C IO error: {}", e),
+            I2CError::Other(s) => write!(f, "I2C error: {}", s),
+        }
+    }
+}
+
+impl error::Error for I2CError {
+    fn description(&self) -> &str {
+        match *self {
+            I2CError::Io(ref e) => e.description(),
+            I2CError::Other(s) => s,
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            I2CError::Io(ref e) => Some(e),
+            I2CError::Other(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for I2CError {
+    fn from(e: io::Error) -> I2CError {
+        I2CError::Io(e)
+    }
+}
+
+/// A connection to an i2c device, abstracted over the backend that
+/// actually talks to the bus (a real `/dev/i2c-*` node, a mock used
+/// for tests, ...)
+///
+/// Implementations are expected to already be "connected" to a
+/// specific slave address by the time they are handed out, so every
+/// operation here maps directly onto a single i2c or SMBus
+/// transaction against that slave.
+pub trait I2CDevice {
+    /// Read data from the device to fill the provided slice
+    fn read(&mut self, data: &mut [u8]) -> I2CResult<()>;
+
+    /// Write the provided buffer to the device
+    fn write(&mut self, data: &[u8]) -> I2CResult<()>;
+
+    /// This sends a single bit to the device, at the place of the Rd/Wr bit
+    fn smbus_write_quick(&mut self, bit: bool) -> I2CResult<()>;
+
+    /// Read a single byte from a device, without specifying a device register
+    fn smbus_read_byte(&mut self) -> I2CResult<u8>;
+
+    /// Send a single byte to a device
+    fn smbus_write_byte(&mut self, value: u8) -> I2CResult<()>;
+
+    /// Read a single byte from a device, from a designated register
+    fn smbus_read_byte_data(&mut self, register: u8) -> I2CResult<u8>;
+
+    /// Write a single byte to a device, to a designated register
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> I2CResult<()>;
+
+    /// Read a single word (2 bytes) from a device, from a designated register
+    fn smbus_read_word_data(&mut self, register: u8) -> I2CResult<u16>;
+
+    /// Write a single word (2 bytes) to a device, to a designated register
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> I2CResult<()>;
+
+    /// Select a register, send 16 bits of data, and read 16 bits of data back
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> I2CResult<u16>;
+
+    /// Read a block of up to 32 bytes from a device, from a designated register
+    fn smbus_read_block_data(&mut self, register: u8) -> I2CResult<Vec<u8>>;
+
+    /// Write a block of up to 32 bytes to a device, to a designated register
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()>;
+
+    /// Write a block of up to 32 bytes using the I2C block write protocol
+    fn smbus_write_i2c_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()>;
+
+    /// Read a block of `len` bytes (up to 32) from a device, from a
+    /// designated register, as a single combined write-then-read
+    /// transaction (SMBus I2C block read)
+    ///
+    /// Unlike issuing a `write` of the register followed by a separate
+    /// `read`, this does not give another master on the bus a chance
+    /// to interleave a transaction (and a sensor a chance to drop the
+    /// sample) between the two steps.
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> I2CResult<Vec<u8>>;
+}
@@ -0,0 +1,23 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rust library for interfacing with i2c devices on Linux platforms
+//!
+//! The `i2cdev::core` module exposes the `I2CDevice` trait that any
+//! i2c device implementation (e.g. `i2cdev::linux::LinuxI2CDevice`)
+//! can be built against, so higher-level sensor drivers do not need
+//! to be coupled to a single backend.
+
+extern crate libc;
+
+pub mod core;
+pub mod linux;
+pub mod mock;
+pub mod sensors;
+
+pub use core::{I2CDevice, I2CError, I2CResult};
@@ -0,0 +1,201 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Linux `/dev/i2c-*` backed implementation of `I2CDevice`
+//!
+//! This talks to the kernel i2c-dev driver directly via `ioctl(2)`,
+//! using the same `I2C_SLAVE` / `I2C_SMBUS` requests as the C
+//! `i2c-tools` userspace.
+
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use libc;
+
+use core::{I2CDevice, I2CResult, I2CError};
+
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const I2C_SMBUS: libc::c_ulong = 0x0720;
+
+const I2C_SMBUS_READ: u8 = 1;
+const I2C_SMBUS_WRITE: u8 = 0;
+
+const I2C_SMBUS_QUICK: u32 = 0;
+const I2C_SMBUS_BYTE: u32 = 1;
+const I2C_SMBUS_BYTE_DATA: u32 = 2;
+const I2C_SMBUS_WORD_DATA: u32 = 3;
+const I2C_SMBUS_PROC_CALL: u32 = 4;
+const I2C_SMBUS_BLOCK_DATA: u32 = 5;
+const I2C_SMBUS_I2C_BLOCK_DATA: u32 = 8;
+
+const I2C_SMBUS_BLOCK_MAX: usize = 32;
+
+#[repr(C)]
+struct i2c_smbus_data {
+    // first byte is the block length for block transactions, the
+    // remainder is the payload
+    block: [u8; I2C_SMBUS_BLOCK_MAX + 2],
+}
+
+impl i2c_smbus_data {
+    fn new() -> i2c_smbus_data {
+        i2c_smbus_data { block: [0; I2C_SMBUS_BLOCK_MAX + 2] }
+    }
+}
+
+#[repr(C)]
+struct i2c_smbus_ioctl_data {
+    read_write: u8,
+    command: u8,
+    size: u32,
+    data: *mut i2c_smbus_data,
+}
+
+/// A connection to an i2c device on Linux, backed by a `/dev/i2c-*` node
+pub struct LinuxI2CDevice {
+    devfile: File,
+    slave_address: u16,
+}
+
+impl LinuxI2CDevice {
+    /// Open the provided i2c device file and set it up to talk to the
+    /// device at `slave_address`
+    pub fn new<P: AsRef<Path>>(path: P, slave_address: u16) -> I2CResult<LinuxI2CDevice> {
+        let devfile = try!(OpenOptions::new().read(true).write(true).open(path.as_ref()));
+        let mut device = LinuxI2CDevice {
+            devfile: devfile,
+            slave_address: 0,
+        };
+        try!(device.set_slave_address(slave_address));
+        Ok(device)
+    }
+
+    /// Set the slave address this device file talks to, as it may be
+    /// shared by several devices on the same bus
+    pub fn set_slave_address(&mut self, slave_address: u16) -> I2CResult<()> {
+        let rc = unsafe {
+            libc::ioctl(self.devfile.as_raw_fd(), I2C_SLAVE, slave_address as libc::c_ulong)
+        };
+        if rc < 0 {
+            return Err(I2CError::Other("ioctl(I2C_SLAVE) failed"));
+        }
+        self.slave_address = slave_address;
+        Ok(())
+    }
+
+    fn smbus_ioctl(&mut self, read_write: u8, command: u8, size: u32, data: Option<&mut i2c_smbus_data>) -> I2CResult<()> {
+        let data_ptr = match data {
+            Some(data) => data as *mut i2c_smbus_data,
+            None => 0 as *mut i2c_smbus_data,
+        };
+        let mut args = i2c_smbus_ioctl_data {
+            read_write: read_write,
+            command: command,
+            size: size,
+            data: data_ptr,
+        };
+        let rc = unsafe { libc::ioctl(self.devfile.as_raw_fd(), I2C_SMBUS, &mut args as *mut i2c_smbus_ioctl_data) };
+        if rc < 0 {
+            Err(I2CError::Other("ioctl(I2C_SMBUS) failed"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl I2CDevice for LinuxI2CDevice {
+    fn read(&mut self, data: &mut [u8]) -> I2CResult<()> {
+        use std::io::Read;
+        try!(self.devfile.read_exact(data));
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> I2CResult<()> {
+        use std::io::Write;
+        try!(self.devfile.write_all(data));
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> I2CResult<()> {
+        let read_write = if bit { I2C_SMBUS_READ } else { I2C_SMBUS_WRITE };
+        self.smbus_ioctl(read_write, 0, I2C_SMBUS_QUICK, None)
+    }
+
+    fn smbus_read_byte(&mut self) -> I2CResult<u8> {
+        let mut data = i2c_smbus_data::new();
+        try!(self.smbus_ioctl(I2C_SMBUS_READ, 0, I2C_SMBUS_BYTE, Some(&mut data)));
+        Ok(data.block[0])
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> I2CResult<()> {
+        self.smbus_ioctl(I2C_SMBUS_WRITE, value, I2C_SMBUS_BYTE, None)
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> I2CResult<u8> {
+        let mut data = i2c_smbus_data::new();
+        try!(self.smbus_ioctl(I2C_SMBUS_READ, register, I2C_SMBUS_BYTE_DATA, Some(&mut data)));
+        Ok(data.block[0])
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> I2CResult<()> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = value;
+        self.smbus_ioctl(I2C_SMBUS_WRITE, register, I2C_SMBUS_BYTE_DATA, Some(&mut data))
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> I2CResult<u16> {
+        let mut data = i2c_smbus_data::new();
+        try!(self.smbus_ioctl(I2C_SMBUS_READ, register, I2C_SMBUS_WORD_DATA, Some(&mut data)));
+        Ok((data.block[0] as u16) | ((data.block[1] as u16) << 8))
+    }
+
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> I2CResult<()> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = (value & 0xff) as u8;
+        data.block[1] = (value >> 8) as u8;
+        self.smbus_ioctl(I2C_SMBUS_WRITE, register, I2C_SMBUS_WORD_DATA, Some(&mut data))
+    }
+
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> I2CResult<u16> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = (value & 0xff) as u8;
+        data.block[1] = (value >> 8) as u8;
+        try!(self.smbus_ioctl(I2C_SMBUS_WRITE, register, I2C_SMBUS_PROC_CALL, Some(&mut data)));
+        Ok((data.block[0] as u16) | ((data.block[1] as u16) << 8))
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> I2CResult<Vec<u8>> {
+        let mut data = i2c_smbus_data::new();
+        try!(self.smbus_ioctl(I2C_SMBUS_READ, register, I2C_SMBUS_BLOCK_DATA, Some(&mut data)));
+        let len = data.block[0] as usize;
+        Ok(data.block[1..1 + len].to_vec())
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = values.len() as u8;
+        data.block[1..1 + values.len()].copy_from_slice(values);
+        self.smbus_ioctl(I2C_SMBUS_WRITE, register, I2C_SMBUS_BLOCK_DATA, Some(&mut data))
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = values.len() as u8;
+        data.block[1..1 + values.len()].copy_from_slice(values);
+        self.smbus_ioctl(I2C_SMBUS_WRITE, register, I2C_SMBUS_I2C_BLOCK_DATA, Some(&mut data))
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> I2CResult<Vec<u8>> {
+        let mut data = i2c_smbus_data::new();
+        data.block[0] = len;
+        try!(self.smbus_ioctl(I2C_SMBUS_READ, register, I2C_SMBUS_I2C_BLOCK_DATA, Some(&mut data)));
+        let actual_len = data.block[0] as usize;
+        Ok(data.block[1..1 + actual_len].to_vec())
+    }
+}
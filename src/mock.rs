@@ -0,0 +1,148 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An in-memory `I2CDevice` for use in unit tests
+//!
+//! `MockI2CDevice` services reads out of a programmable register map
+//! and records every write it receives, so sensor drivers written
+//! against the `I2CDevice` trait can be exercised without real
+//! hardware.
+
+use std::collections::HashMap;
+use core::{I2CDevice, I2CResult, I2CError};
+
+/// A recorded write, as handed to `write`, one of the `smbus_write_*`
+/// methods or `smbus_process_word`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockWrite {
+    Quick(bool),
+    Byte(u8),
+    ByteData { register: u8, value: u8 },
+    WordData { register: u8, value: u16 },
+    BlockData { register: u8, values: Vec<u8> },
+    I2CBlockData { register: u8, values: Vec<u8> },
+    Raw(Vec<u8>),
+}
+
+/// In-memory stand-in for a real i2c device
+///
+/// Registers are serviced from a simple `HashMap<u8, u8>`; set up the
+/// map ahead of time with `set_register` (or `new` with a prefilled
+/// map) and then hand the mock to whatever code expects an
+/// `I2CDevice`.  Every write performed against the mock is appended
+/// to `writes` for later assertions.
+pub struct MockI2CDevice {
+    pub registers: HashMap<u8, u8>,
+    pub writes: Vec<MockWrite>,
+}
+
+impl MockI2CDevice {
+    /// Create a mock with no registers populated
+    pub fn new() -> MockI2CDevice {
+        MockI2CDevice {
+            registers: HashMap::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Set the value that will be returned when `register` is read
+    pub fn set_register(&mut self, register: u8, value: u8) {
+        self.registers.insert(register, value);
+    }
+
+    fn register_or_err(&self, register: u8) -> I2CResult<u8> {
+        self.registers
+            .get(&register)
+            .cloned()
+            .ok_or(I2CError::Other("MockI2CDevice: no value set for register"))
+    }
+}
+
+impl I2CDevice for MockI2CDevice {
+    fn read(&mut self, data: &mut [u8]) -> I2CResult<()> {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = try!(self.register_or_err(i as u8));
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> I2CResult<()> {
+        self.writes.push(MockWrite::Raw(data.to_vec()));
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> I2CResult<()> {
+        self.writes.push(MockWrite::Quick(bit));
+        Ok(())
+    }
+
+    fn smbus_read_byte(&mut self) -> I2CResult<u8> {
+        self.register_or_err(0)
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> I2CResult<()> {
+        self.writes.push(MockWrite::Byte(value));
+        Ok(())
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> I2CResult<u8> {
+        self.register_or_err(register)
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> I2CResult<()> {
+        self.writes.push(MockWrite::ByteData { register: register, value: value });
+        Ok(())
+    }
+
+    fn smbus_read_word_data(&mut self, register: u8) -> I2CResult<u16> {
+        let lo = try!(self.register_or_err(register)) as u16;
+        let hi = try!(self.register_or_err(register + 1)) as u16;
+        Ok(lo | (hi << 8))
+    }
+
+    fn smbus_write_word_data(&mut self, register: u8, value: u16) -> I2CResult<()> {
+        self.writes.push(MockWrite::WordData { register: register, value: value });
+        Ok(())
+    }
+
+    fn smbus_process_word(&mut self, register: u8, value: u16) -> I2CResult<u16> {
+        self.writes.push(MockWrite::WordData { register: register, value: value });
+        self.smbus_read_word_data(register)
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> I2CResult<Vec<u8>> {
+        let mut values = Vec::new();
+        let mut reg = register;
+        while values.len() < 32 {
+            match self.register_or_err(reg) {
+                Ok(value) => values.push(value),
+                Err(_) => break,
+            }
+            reg = reg.wrapping_add(1);
+        }
+        Ok(values)
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()> {
+        self.writes.push(MockWrite::BlockData { register: register, values: values.to_vec() });
+        Ok(())
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, register: u8, values: &[u8]) -> I2CResult<()> {
+        self.writes.push(MockWrite::I2CBlockData { register: register, values: values.to_vec() });
+        Ok(())
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> I2CResult<Vec<u8>> {
+        let mut values = Vec::with_capacity(len as usize);
+        for offset in 0..len {
+            values.push(try!(self.register_or_err(register.wrapping_add(offset))));
+        }
+        Ok(values)
+    }
+}
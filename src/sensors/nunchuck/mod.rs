@@ -0,0 +1,525 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Driver for the Wii Nunchuck, an i2c device found in the Wii Remote
+//! extension port
+
+use core::{I2CDevice, I2CResult, I2CError};
+
+#[cfg(target_os = "linux")]
+pub mod uinput;
+
+/// The fixed i2c slave address every Nunchuck (and clone) answers to
+pub const NUNCHUCK_SLAVE_ADDR: u16 = 0x52;
+
+/// A single sample read from a Wii Nunchuck
+#[derive(Debug)]
+pub struct NunchuckReading {
+    pub joystick_x: u8,
+    pub joystick_y: u8,
+    pub accel_x: u16, // 10-bit
+    pub accel_y: u16, // 10-bit
+    pub accel_z: u16, // 10-bit
+    pub c_button_pressed: bool,
+    pub z_button_pressed: bool,
+}
+
+impl NunchuckReading {
+    pub fn from_data(data: &[u8]) -> Option<NunchuckReading> {
+        if data.len() < 6 {
+            None
+        } else {
+            Some(NunchuckReading {
+                joystick_x: data[0],
+                joystick_y: data[1],
+                accel_x: (data[2] as u16) << 2 | ((data[5] as u16 >> 6) & 0b11),
+                accel_y: (data[3] as u16) << 2 | ((data[5] as u16 >> 4) & 0b11),
+                accel_z: (data[4] as u16) << 2 | ((data[5] as u16 >> 2) & 0b11),
+                c_button_pressed: (data[5] & 0b10) == 0,
+                z_button_pressed: (data[5] & 0b01) == 0,
+            })
+        }
+    }
+
+    /// Joystick X, centered on `calibration` and normalized to roughly
+    /// `-1.0..=1.0`
+    pub fn joystick_x_normalized(&self, calibration: &NunchuckCalibration) -> f32 {
+        normalize_u8(self.joystick_x, calibration.joystick_x_zero)
+    }
+
+    /// Joystick Y, centered on `calibration` and normalized to roughly
+    /// `-1.0..=1.0`
+    pub fn joystick_y_normalized(&self, calibration: &NunchuckCalibration) -> f32 {
+        normalize_u8(self.joystick_y, calibration.joystick_y_zero)
+    }
+
+    /// Accelerometer X, as a signed offset from `calibration`'s zero point
+    pub fn accel_x_offset(&self, calibration: &NunchuckCalibration) -> i16 {
+        self.accel_x as i16 - calibration.accel_x_zero as i16
+    }
+
+    /// Accelerometer Y, as a signed offset from `calibration`'s zero point
+    pub fn accel_y_offset(&self, calibration: &NunchuckCalibration) -> i16 {
+        self.accel_y as i16 - calibration.accel_y_zero as i16
+    }
+
+    /// Accelerometer Z, as a signed offset from `calibration`'s zero point
+    pub fn accel_z_offset(&self, calibration: &NunchuckCalibration) -> i16 {
+        self.accel_z as i16 - calibration.accel_z_zero as i16
+    }
+}
+
+/// Normalize a `u8` reading to roughly `-1.0..=1.0` around `zero`,
+/// scaling each side of center by however much range it has to work
+/// with so a reading pinned at 0 or 255 still maps to exactly -1.0/1.0
+fn normalize_u8(value: u8, zero: u8) -> f32 {
+    let diff = value as f32 - zero as f32;
+    let range = if diff >= 0.0 {
+        255.0 - zero as f32
+    } else {
+        zero as f32
+    };
+    if range == 0.0 {
+        0.0
+    } else {
+        (diff / range).max(-1.0).min(1.0)
+    }
+}
+
+/// Per-axis zero points used to center a `NunchuckReading`
+///
+/// Raw joystick axes center near 127/128 and the 10-bit accelerometer
+/// axes center near 512, but these vary per unit. Capture the actual
+/// resting values with `Nunchuck::calibrate` (or supply your own via
+/// `Nunchuck::with_calibration`) instead of hardcoding these magic
+/// constants in every caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NunchuckCalibration {
+    pub joystick_x_zero: u8,
+    pub joystick_y_zero: u8,
+    pub accel_x_zero: u16,
+    pub accel_y_zero: u16,
+    pub accel_z_zero: u16,
+}
+
+impl Default for NunchuckCalibration {
+    fn default() -> NunchuckCalibration {
+        NunchuckCalibration {
+            joystick_x_zero: 127,
+            joystick_y_zero: 128,
+            accel_x_zero: 512,
+            accel_y_zero: 512,
+            accel_z_zero: 512,
+        }
+    }
+}
+
+impl NunchuckCalibration {
+    fn from_reading(reading: &NunchuckReading) -> NunchuckCalibration {
+        NunchuckCalibration {
+            joystick_x_zero: reading.joystick_x,
+            joystick_y_zero: reading.joystick_y,
+            accel_x_zero: reading.accel_x,
+            accel_y_zero: reading.accel_y,
+            accel_z_zero: reading.accel_z,
+        }
+    }
+}
+
+/// Which handshake to perform on init, and therefore how samples
+/// coming back from the device need to be interpreted
+///
+/// Genuine Nunchucks (and some clones) accept the unencrypted
+/// handshake and hand back plain data. Cheaper clones only accept the
+/// classic `0x40 0x00` handshake and scramble every sample, which
+/// costs an extra descramble pass per byte on every read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitMode {
+    Unencrypted,
+    Encrypted,
+}
+
+/// A Wii Nunchuck, generic over whatever `I2CDevice` it is plugged
+/// into (a real `LinuxI2CDevice`, a `MockI2CDevice` in tests, ...)
+pub struct Nunchuck<T: I2CDevice> {
+    i2cdev: T,
+    init_mode: InitMode,
+    calibration: NunchuckCalibration,
+}
+
+impl<T: I2CDevice> Nunchuck<T> {
+    /// Create a new Wii Nunchuck, using the standard unencrypted
+    /// handshake and the default calibration
+    ///
+    /// This method will send the required init sequence in order to
+    /// read data in the future. Use `with_init_mode` if the device is
+    /// a clone that requires the encrypted handshake instead, or
+    /// `calibrate` afterwards to zero the joystick/accelerometer
+    /// against the device's actual resting position.
+    pub fn new(i2cdev: T) -> I2CResult<Nunchuck<T>> {
+        Nunchuck::with_init_mode(i2cdev, InitMode::Unencrypted)
+    }
+
+    /// Create a new Wii Nunchuck, performing the given `InitMode`'s
+    /// handshake, with the default calibration
+    pub fn with_init_mode(i2cdev: T, init_mode: InitMode) -> I2CResult<Nunchuck<T>> {
+        Nunchuck::with_calibration(i2cdev, init_mode, NunchuckCalibration::default())
+    }
+
+    /// Create a new Wii Nunchuck with a caller-supplied calibration,
+    /// e.g. one captured from this exact unit on a previous run
+    pub fn with_calibration(i2cdev: T, init_mode: InitMode, calibration: NunchuckCalibration) -> I2CResult<Nunchuck<T>> {
+        let mut nunchuck = Nunchuck { i2cdev: i2cdev, init_mode: init_mode, calibration: calibration };
+        try!(nunchuck.init());
+        Ok(nunchuck)
+    }
+
+    /// Sample the device's current resting position and adopt it as
+    /// this `Nunchuck`'s calibration
+    ///
+    /// The joystick/accelerometer must be at rest (centered, flat)
+    /// when this is called.
+    pub fn calibrate(&mut self) -> I2CResult<()> {
+        let reading = try!(self.read());
+        self.calibration = NunchuckCalibration::from_reading(&reading);
+        Ok(())
+    }
+
+    /// The calibration currently in effect
+    pub fn calibration(&self) -> &NunchuckCalibration {
+        &self.calibration
+    }
+
+    /// Send the init sequence to the Wii Nunchuck
+    pub fn init(&mut self) -> I2CResult<()> {
+        match self.init_mode {
+            InitMode::Unencrypted => {
+                // These registers must be written; the documentation is a bit
+                // lacking but it appears this is some kind of handshake to
+                // perform unencrypted data tranfers
+                try!(self.i2cdev.smbus_write_byte_data(0xF0, 0x55));
+                try!(self.i2cdev.smbus_write_byte_data(0xFB, 0x00));
+            }
+            InitMode::Encrypted => {
+                // The classic init sequence; clones that reject the
+                // unencrypted handshake above accept this one, but
+                // scramble every byte they send back afterwards
+                try!(self.i2cdev.smbus_write_byte_data(0x40, 0x00));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(&mut self) -> I2CResult<NunchuckReading> {
+        let buf = try!(self.sample());
+        match NunchuckReading::from_data(&buf) {
+            Some(reading) => Ok(reading),
+            None => Err(I2CError::Other("Unable to Parse Data")),
+        }
+    }
+
+    /// Read a sample formatted as a Classic Controller report rather
+    /// than a Nunchuck one; use `identify` first to check which
+    /// extension is actually plugged in
+    pub fn read_classic_controller(&mut self) -> I2CResult<ClassicControllerReading> {
+        let buf = try!(self.sample());
+        match ClassicControllerReading::from_data(&buf) {
+            Some(reading) => Ok(reading),
+            None => Err(I2CError::Other("Unable to Parse Data")),
+        }
+    }
+
+    /// Request and read back the six raw data bytes of a sample in a
+    /// single combined transaction, descrambling them first if
+    /// `InitMode::Encrypted` was used
+    ///
+    /// This used to be a `smbus_write_byte(0x00)` followed by a 10ms
+    /// sleep and a separate `read`; that left a window for another
+    /// bus master to interleave a transaction and for the sample to
+    /// be dropped under contention. `smbus_read_i2c_block_data` reads
+    /// register `0x00` as one combined write-then-read transaction (an
+    /// SMBus I2C block read), so there is nothing to sleep through.
+    fn sample(&mut self) -> I2CResult<[u8; 6]> {
+        let block = try!(self.i2cdev.smbus_read_i2c_block_data(0x00, 6));
+        if block.len() < 6 {
+            return Err(I2CError::Other("Unable to Parse Data"));
+        }
+        let mut buf: [u8; 6] = [0; 6];
+        buf.copy_from_slice(&block[..6]);
+        if self.init_mode == InitMode::Encrypted {
+            for byte in buf.iter_mut() {
+                *byte = (*byte ^ 0x17).wrapping_add(0x17);
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Identify which Wii extension controller is plugged in by
+    /// reading its six identity bytes from register `0xFA`
+    ///
+    /// The Nunchuck shares its bus protocol and `0x52` slave address
+    /// with other Wii extensions (e.g. the Classic Controller), so
+    /// this should be called right after `init` to find out what is
+    /// actually connected before deciding whether to call `read` or
+    /// `read_classic_controller`.
+    pub fn identify(&mut self) -> I2CResult<ExtensionId> {
+        let block = try!(self.i2cdev.smbus_read_i2c_block_data(0xFA, 6));
+        if block.len() < 6 {
+            return Err(I2CError::Other("Unable to Parse Data"));
+        }
+        let mut buf: [u8; 6] = [0; 6];
+        buf.copy_from_slice(&block[..6]);
+        if self.init_mode == InitMode::Encrypted {
+            for byte in buf.iter_mut() {
+                *byte = (*byte ^ 0x17).wrapping_add(0x17);
+            }
+        }
+        if buf == NUNCHUCK_IDENT {
+            Ok(ExtensionId::Nunchuck)
+        } else if buf == CLASSIC_CONTROLLER_IDENT {
+            Ok(ExtensionId::ClassicController)
+        } else {
+            Ok(ExtensionId::Unknown(buf))
+        }
+    }
+}
+
+const NUNCHUCK_IDENT: [u8; 6] = [0x00, 0x00, 0xA4, 0x20, 0x00, 0x00];
+const CLASSIC_CONTROLLER_IDENT: [u8; 6] = [0x00, 0x00, 0xA4, 0x20, 0x01, 0x01];
+
+/// Which Wii extension controller identified itself at the other end
+/// of the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionId {
+    Nunchuck,
+    ClassicController,
+    /// The six raw identity bytes read back, for extensions this
+    /// crate doesn't know how to decode yet
+    Unknown([u8; 6]),
+}
+
+/// A single sample read from a Classic Controller
+#[derive(Debug)]
+pub struct ClassicControllerReading {
+    pub left_stick_x: u8,  // 6-bit
+    pub left_stick_y: u8,  // 6-bit
+    pub right_stick_x: u8, // 5-bit
+    pub right_stick_y: u8, // 5-bit
+    pub left_trigger: u8,  // 5-bit
+    pub right_trigger: u8, // 5-bit
+    pub dpad_up: bool,
+    pub dpad_down: bool,
+    pub dpad_left: bool,
+    pub dpad_right: bool,
+    pub button_a: bool,
+    pub button_b: bool,
+    pub button_x: bool,
+    pub button_y: bool,
+    pub button_l: bool,
+    pub button_zl: bool,
+    pub button_r: bool,
+    pub button_zr: bool,
+    pub button_minus: bool,
+    pub button_plus: bool,
+    pub button_home: bool,
+}
+
+impl ClassicControllerReading {
+    pub fn from_data(data: &[u8]) -> Option<ClassicControllerReading> {
+        if data.len() < 6 {
+            return None;
+        }
+
+        let right_stick_x = ((data[0] >> 6) & 0b11) << 3 | ((data[1] >> 6) & 0b11) << 1 |
+                             ((data[2] >> 7) & 0b1);
+        let left_trigger = ((data[2] >> 5) & 0b11) << 3 | ((data[3] >> 5) & 0b111);
+
+        Some(ClassicControllerReading {
+            left_stick_x: data[0] & 0b0011_1111,
+            left_stick_y: data[1] & 0b0011_1111,
+            right_stick_x: right_stick_x,
+            right_stick_y: data[2] & 0b0001_1111,
+            left_trigger: left_trigger,
+            right_trigger: data[3] & 0b0001_1111,
+            dpad_up: (data[4] & 0b0000_0001) == 0,
+            button_l: (data[4] & 0b0000_0010) == 0,
+            button_minus: (data[4] & 0b0000_0100) == 0,
+            button_home: (data[4] & 0b0000_1000) == 0,
+            button_plus: (data[4] & 0b0001_0000) == 0,
+            button_r: (data[4] & 0b0010_0000) == 0,
+            dpad_down: (data[4] & 0b0100_0000) == 0,
+            dpad_right: (data[4] & 0b1000_0000) == 0,
+            button_zl: (data[5] & 0b0000_0001) == 0,
+            button_b: (data[5] & 0b0000_0010) == 0,
+            button_y: (data[5] & 0b0000_0100) == 0,
+            button_a: (data[5] & 0b0000_1000) == 0,
+            button_x: (data[5] & 0b0001_0000) == 0,
+            button_zr: (data[5] & 0b0010_0000) == 0,
+            dpad_left: (data[5] & 0b0100_0000) == 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::MockI2CDevice;
+
+    fn mock_with_sample(sample: [u8; 6]) -> MockI2CDevice {
+        let mut mock = MockI2CDevice::new();
+        mock.set_register(0xF0, 0);
+        mock.set_register(0xFB, 0);
+        for (i, byte) in sample.iter().enumerate() {
+            mock.set_register(i as u8, *byte);
+        }
+        mock
+    }
+
+    #[test]
+    fn decodes_joystick_accel_and_buttons() {
+        // bits 1/0 set means both buttons are released (the wire
+        // encoding is active-low)
+        let mock = mock_with_sample([0x7e, 0x81, 0x12, 0x34, 0x56, 0b1010_0111]);
+        let mut nunchuck = Nunchuck::new(mock).unwrap();
+        let reading = nunchuck.read().unwrap();
+
+        assert_eq!(reading.joystick_x, 0x7e);
+        assert_eq!(reading.joystick_y, 0x81);
+        assert_eq!(reading.accel_x, (0x12 << 2) | ((0b1010_0111 >> 6) & 0b11));
+        assert_eq!(reading.accel_y, (0x34 << 2) | ((0b1010_0111 >> 4) & 0b11));
+        assert_eq!(reading.accel_z, (0x56 << 2) | ((0b1010_0111 >> 2) & 0b11));
+        assert_eq!(reading.c_button_pressed, false);
+        assert_eq!(reading.z_button_pressed, false);
+    }
+
+    #[test]
+    fn init_writes_unencrypted_handshake() {
+        let mock = mock_with_sample([0; 6]);
+        let nunchuck = Nunchuck::new(mock).unwrap();
+        assert!(nunchuck.i2cdev.writes.iter().any(|w| *w == ::mock::MockWrite::ByteData { register: 0xF0, value: 0x55 }));
+        assert!(nunchuck.i2cdev.writes.iter().any(|w| *w == ::mock::MockWrite::ByteData { register: 0xFB, value: 0x00 }));
+    }
+
+    #[test]
+    fn init_writes_encrypted_handshake() {
+        let mock = mock_with_sample([0; 6]);
+        let nunchuck = Nunchuck::with_init_mode(mock, InitMode::Encrypted).unwrap();
+        assert!(nunchuck.i2cdev.writes.iter().any(|w| *w == ::mock::MockWrite::ByteData { register: 0x40, value: 0x00 }));
+    }
+
+    #[test]
+    fn encrypted_mode_descrambles_sample() {
+        let decoded: [u8; 6] = [0x7e, 0x81, 0x12, 0x34, 0x56, 0b1010_0100];
+        let scrambled: Vec<u8> = decoded.iter().map(|b| b.wrapping_sub(0x17) ^ 0x17).collect();
+        let mut sample = [0u8; 6];
+        sample.copy_from_slice(&scrambled);
+
+        let mock = mock_with_sample(sample);
+        let mut nunchuck = Nunchuck::with_init_mode(mock, InitMode::Encrypted).unwrap();
+        let reading = nunchuck.read().unwrap();
+
+        assert_eq!(reading.joystick_x, decoded[0]);
+        assert_eq!(reading.joystick_y, decoded[1]);
+    }
+
+    #[test]
+    fn calibrate_zeroes_against_current_reading() {
+        let mock = mock_with_sample([140, 90, 10, 20, 30, 0b1111_1111]);
+        let mut nunchuck = Nunchuck::new(mock).unwrap();
+        nunchuck.calibrate().unwrap();
+
+        let reading = nunchuck.read().unwrap();
+        assert_eq!(reading.joystick_x_normalized(nunchuck.calibration()), 0.0);
+        assert_eq!(reading.joystick_y_normalized(nunchuck.calibration()), 0.0);
+        assert_eq!(reading.accel_x_offset(nunchuck.calibration()), 0);
+    }
+
+    #[test]
+    fn normalized_joystick_tracks_full_deflection() {
+        let calibration = NunchuckCalibration::default();
+        let reading = NunchuckReading::from_data(&[255, 0, 0, 0, 0, 0b1111_1111]).unwrap();
+        assert_eq!(reading.joystick_x_normalized(&calibration), 1.0);
+        assert_eq!(reading.joystick_y_normalized(&calibration), -1.0);
+    }
+
+    #[test]
+    fn identify_recognizes_nunchuck_and_classic_controller() {
+        let mut mock = mock_with_sample([0; 6]);
+        for (i, byte) in NUNCHUCK_IDENT.iter().enumerate() {
+            mock.set_register(0xFA + i as u8, *byte);
+        }
+        let mut nunchuck = Nunchuck::new(mock).unwrap();
+        assert_eq!(nunchuck.identify().unwrap(), ExtensionId::Nunchuck);
+
+        let mut mock = mock_with_sample([0; 6]);
+        for (i, byte) in CLASSIC_CONTROLLER_IDENT.iter().enumerate() {
+            mock.set_register(0xFA + i as u8, *byte);
+        }
+        let mut nunchuck = Nunchuck::new(mock).unwrap();
+        assert_eq!(nunchuck.identify().unwrap(), ExtensionId::ClassicController);
+    }
+
+    #[test]
+    fn classic_controller_decodes_sticks_triggers_and_buttons() {
+        // Several fields are spread across shared bytes, so build each
+        // byte explicitly from its component bitfields rather than
+        // picking numbers that happen to read right for only one field.
+        let left_stick_x = 0b11_1111u8; // 6 bits, fully right
+        let left_stick_y = 0b11_1111u8; // 6 bits, fully up
+        let right_stick_x = 0b1_1111u8; // 5 bits, fully right
+        let right_stick_y = 0b1_1111u8; // 5 bits, fully up
+        let left_trigger = 0b1_1111u8; // 5 bits, fully pressed
+        let right_trigger = 0b1_1111u8; // 5 bits, fully pressed
+
+        let data0 = ((right_stick_x >> 3) & 0b11) << 6 | left_stick_x;
+        let data1 = ((right_stick_x >> 1) & 0b11) << 6 | left_stick_y;
+        let data2 = (right_stick_x & 0b1) << 7 | ((left_trigger >> 3) & 0b11) << 5 | right_stick_y;
+        let data3 = (left_trigger & 0b111) << 5 | right_trigger;
+        let data = [data0, data1, data2, data3, 0xff, 0xff];
+        let reading = ClassicControllerReading::from_data(&data).unwrap();
+        assert_eq!(reading.left_stick_x, 0x3f);
+        assert_eq!(reading.left_stick_y, 0x3f);
+        assert_eq!(reading.right_stick_x, 0x1f);
+        assert_eq!(reading.right_stick_y, 0x1f);
+        assert_eq!(reading.left_trigger, 0x1f);
+        assert_eq!(reading.right_trigger, 0x1f);
+        assert!(!reading.dpad_up);
+        assert!(!reading.dpad_down);
+        assert!(!reading.dpad_left);
+        assert!(!reading.dpad_right);
+        assert!(!reading.button_a);
+        assert!(!reading.button_b);
+        assert!(!reading.button_x);
+        assert!(!reading.button_y);
+        assert!(!reading.button_l);
+        assert!(!reading.button_zl);
+        assert!(!reading.button_r);
+        assert!(!reading.button_zr);
+        assert!(!reading.button_minus);
+        assert!(!reading.button_plus);
+        assert!(!reading.button_home);
+
+        let pressed = [data0, data1, data2, data3, 0x00, 0x00];
+        let reading = ClassicControllerReading::from_data(&pressed).unwrap();
+        assert!(reading.dpad_up);
+        assert!(reading.dpad_down);
+        assert!(reading.dpad_left);
+        assert!(reading.dpad_right);
+        assert!(reading.button_a);
+        assert!(reading.button_b);
+        assert!(reading.button_x);
+        assert!(reading.button_y);
+        assert!(reading.button_l);
+        assert!(reading.button_zl);
+        assert!(reading.button_r);
+        assert!(reading.button_zr);
+        assert!(reading.button_minus);
+        assert!(reading.button_plus);
+        assert!(reading.button_home);
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2015, Paul Osborne <osbpau@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/license/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option.  This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bridge a Nunchuck into a virtual Linux input device via `/dev/uinput`
+//!
+//! This polls `Nunchuck::read` at a configurable interval and turns
+//! each reading into `EV_ABS`/`EV_REL`/`EV_KEY` events on a registered
+//! uinput device, so the Nunchuck shows up to the rest of the OS (X11,
+//! Wayland, jstest, ...) as an ordinary joystick or mouse. Build one
+//! with `Builder`.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::slice;
+use std::thread;
+use std::time::Duration;
+
+use libc;
+
+use core::I2CDevice;
+use core::{I2CResult, I2CError};
+use super::{Nunchuck, NunchuckCalibration, NunchuckReading};
+
+const UINPUT_PATH: &'static str = "/dev/uinput";
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+const ABS_CNT: usize = 64;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+
+const SYN_REPORT: u16 = 0;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02; // accelerometer X
+const ABS_RX: u16 = 0x03; // accelerometer Y
+const ABS_RY: u16 = 0x04; // accelerometer Z
+
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+
+const UI_SET_EVBIT: libc::c_ulong = 0x40045564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x40045565;
+const UI_SET_RELBIT: libc::c_ulong = 0x40045566;
+const UI_SET_ABSBIT: libc::c_ulong = 0x40045567;
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+
+#[repr(C)]
+struct input_id {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
+#[repr(C)]
+struct uinput_user_dev {
+    name: [u8; UINPUT_MAX_NAME_SIZE],
+    id: input_id,
+    ff_effects_max: u32,
+    absmax: [i32; ABS_CNT],
+    absmin: [i32; ABS_CNT],
+    absfuzz: [i32; ABS_CNT],
+    absflat: [i32; ABS_CNT],
+}
+
+#[repr(C)]
+struct input_event {
+    time: libc::timeval,
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+/// How joystick/accelerometer deflection is translated into uinput
+/// events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Report the joystick as `ABS_X`/`ABS_Y` and the accelerometer as
+    /// `ABS_Z` and friends, as a regular analog joystick would
+    Joystick,
+    /// Report the joystick as relative cursor motion (`REL_X`/`REL_Y`),
+    /// scaled by `sensitivity`, as if it were a mouse
+    MouseCursor { sensitivity: f32 },
+}
+
+/// Builds a `Bridge` that polls a Nunchuck and drives a uinput device
+pub struct Builder {
+    mode: Mode,
+    poll_interval: Duration,
+    device_name: String,
+}
+
+impl Builder {
+    /// Start from the defaults: joystick mode, a 20ms poll interval,
+    /// device name "i2cdev Wii Nunchuck"
+    pub fn new() -> Builder {
+        Builder {
+            mode: Mode::Joystick,
+            poll_interval: Duration::from_millis(20),
+            device_name: "i2cdev Wii Nunchuck".to_string(),
+        }
+    }
+
+    /// Choose how the joystick is reported: as absolute axes
+    /// (`Mode::Joystick`) or as relative mouse motion
+    /// (`Mode::MouseCursor`); C/Z are the left/right mouse buttons in
+    /// either mode
+    pub fn mode(mut self, mode: Mode) -> Builder {
+        self.mode = mode;
+        self
+    }
+
+    /// How often to poll the Nunchuck for a new sample
+    pub fn poll_interval(mut self, interval: Duration) -> Builder {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Open `/dev/uinput` and register the virtual device
+    pub fn build(self) -> I2CResult<Bridge> {
+        let uinput_file = try!(OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(Path::new(UINPUT_PATH)));
+
+        try!(register_bits(&uinput_file, &self.mode));
+        try!(create_device(&uinput_file, &self.device_name));
+
+        Ok(Bridge {
+            uinput_file: uinput_file,
+            mode: self.mode,
+            poll_interval: self.poll_interval,
+        })
+    }
+}
+
+fn ioctl_set(file: &File, request: libc::c_ulong, arg: libc::c_int) -> I2CResult<()> {
+    let rc = unsafe { libc::ioctl(file.as_raw_fd(), request, arg) };
+    if rc < 0 {
+        Err(I2CError::Other("uinput ioctl failed"))
+    } else {
+        Ok(())
+    }
+}
+
+fn register_bits(file: &File, mode: &Mode) -> I2CResult<()> {
+    try!(ioctl_set(file, UI_SET_EVBIT, EV_KEY as libc::c_int));
+    try!(ioctl_set(file, UI_SET_KEYBIT, BTN_LEFT as libc::c_int));
+    try!(ioctl_set(file, UI_SET_KEYBIT, BTN_RIGHT as libc::c_int));
+
+    match *mode {
+        Mode::Joystick => {
+            try!(ioctl_set(file, UI_SET_EVBIT, EV_ABS as libc::c_int));
+            try!(ioctl_set(file, UI_SET_ABSBIT, ABS_X as libc::c_int));
+            try!(ioctl_set(file, UI_SET_ABSBIT, ABS_Y as libc::c_int));
+            try!(ioctl_set(file, UI_SET_ABSBIT, ABS_Z as libc::c_int));
+            try!(ioctl_set(file, UI_SET_ABSBIT, ABS_RX as libc::c_int));
+            try!(ioctl_set(file, UI_SET_ABSBIT, ABS_RY as libc::c_int));
+        }
+        Mode::MouseCursor { .. } => {
+            try!(ioctl_set(file, UI_SET_EVBIT, EV_REL as libc::c_int));
+            try!(ioctl_set(file, UI_SET_RELBIT, REL_X as libc::c_int));
+            try!(ioctl_set(file, UI_SET_RELBIT, REL_Y as libc::c_int));
+        }
+    }
+    Ok(())
+}
+
+fn create_device(file: &File, name: &str) -> I2CResult<()> {
+    let mut dev: uinput_user_dev = unsafe { mem::zeroed() };
+    for (dst, src) in dev.name.iter_mut().zip(name.bytes()) {
+        *dst = src;
+    }
+    dev.id.bustype = 0x06; // BUS_VIRTUAL
+    dev.id.vendor = 0x0001;
+    dev.id.product = 0x0001;
+    dev.id.version = 1;
+    for axis in 0..ABS_CNT {
+        dev.absmin[axis] = -1;
+        dev.absmax[axis] = 1;
+    }
+    // ABS_X/ABS_Y are emitted via scale_to_i16, and the accelerometer
+    // axes are emitted as a raw i16 offset, so both need the full i16
+    // range advertised or consumers will normalize/clamp the values
+    // against the wrong scale
+    dev.absmin[ABS_X as usize] = i16::min_value() as i32;
+    dev.absmax[ABS_X as usize] = i16::max_value() as i32;
+    dev.absmin[ABS_Y as usize] = i16::min_value() as i32;
+    dev.absmax[ABS_Y as usize] = i16::max_value() as i32;
+    dev.absmin[ABS_Z as usize] = i16::min_value() as i32;
+    dev.absmax[ABS_Z as usize] = i16::max_value() as i32;
+    dev.absmin[ABS_RX as usize] = i16::min_value() as i32;
+    dev.absmax[ABS_RX as usize] = i16::max_value() as i32;
+    dev.absmin[ABS_RY as usize] = i16::min_value() as i32;
+    dev.absmax[ABS_RY as usize] = i16::max_value() as i32;
+
+    let bytes = unsafe {
+        slice::from_raw_parts(&dev as *const uinput_user_dev as *const u8,
+                               mem::size_of::<uinput_user_dev>())
+    };
+    let mut file = file;
+    try!(file.write_all(bytes));
+
+    let rc = unsafe { libc::ioctl(file.as_raw_fd(), UI_DEV_CREATE, 0) };
+    if rc < 0 {
+        return Err(I2CError::Other("UI_DEV_CREATE failed"));
+    }
+    Ok(())
+}
+
+/// A live connection between a Nunchuck and a uinput device, created
+/// via `Builder`
+pub struct Bridge {
+    uinput_file: File,
+    mode: Mode,
+    poll_interval: Duration,
+}
+
+impl Bridge {
+    /// Poll `nunchuck` forever at the configured interval, emitting a
+    /// uinput event batch for every reading
+    pub fn run<T: I2CDevice>(&mut self, nunchuck: &mut Nunchuck<T>) -> I2CResult<()> {
+        loop {
+            let reading = try!(nunchuck.read());
+            try!(self.emit(&reading, nunchuck.calibration()));
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    fn emit(&mut self, reading: &NunchuckReading, calibration: &NunchuckCalibration) -> I2CResult<()> {
+        match self.mode {
+            Mode::Joystick => {
+                try!(self.write_event(EV_ABS, ABS_X, scale_to_i16(reading.joystick_x_normalized(calibration))));
+                try!(self.write_event(EV_ABS, ABS_Y, scale_to_i16(reading.joystick_y_normalized(calibration))));
+                try!(self.write_event(EV_ABS, ABS_Z, reading.accel_x_offset(calibration) as i32));
+                try!(self.write_event(EV_ABS, ABS_RX, reading.accel_y_offset(calibration) as i32));
+                try!(self.write_event(EV_ABS, ABS_RY, reading.accel_z_offset(calibration) as i32));
+            }
+            Mode::MouseCursor { sensitivity } => {
+                let dx = (reading.joystick_x_normalized(calibration) * sensitivity) as i32;
+                let dy = (reading.joystick_y_normalized(calibration) * sensitivity) as i32;
+                try!(self.write_event(EV_REL, REL_X, dx));
+                try!(self.write_event(EV_REL, REL_Y, dy));
+            }
+        }
+        try!(self.write_event(EV_KEY, BTN_LEFT, reading.c_button_pressed as i32));
+        try!(self.write_event(EV_KEY, BTN_RIGHT, reading.z_button_pressed as i32));
+        self.write_event(EV_SYN, SYN_REPORT, 0)
+    }
+
+    fn write_event(&mut self, kind: u16, code: u16, value: i32) -> I2CResult<()> {
+        let event = input_event {
+            time: unsafe { mem::zeroed() },
+            kind: kind,
+            code: code,
+            value: value,
+        };
+        let bytes = unsafe {
+            slice::from_raw_parts(&event as *const input_event as *const u8,
+                                   mem::size_of::<input_event>())
+        };
+        try!(self.uinput_file.write_all(bytes));
+        Ok(())
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.uinput_file.as_raw_fd(), UI_DEV_DESTROY, 0);
+        }
+    }
+}
+
+fn scale_to_i16(normalized: f32) -> i32 {
+    (normalized * i16::max_value() as f32) as i32
+}